@@ -0,0 +1,214 @@
+use crate::backend_error::PointsViewerError;
+use crate::state::AppState;
+use actix_web::{web, HttpResponse};
+use point_viewer::octree::{NodeId, Octree};
+use rstar::{RTree, RTreeObject, AABB};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// How a node's content changed between the old and new generation of an octree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffOp {
+    /// The node exists only in the new tree.
+    Added,
+    /// The node exists only in the old tree.
+    Removed,
+    /// The node exists in both trees but its point payload hash differs.
+    Changed,
+}
+
+/// A single entry in a [`DiffManifest`]: which node changed, how, and where it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffEntry {
+    pub node_id: String,
+    pub op: DiffOp,
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+/// The set of node-level operations that turn `old` into `new`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiffManifest {
+    pub entries: Vec<DiffEntry>,
+}
+
+/// A node together with its bounding box and a stable hash of its encoded
+/// point payload, indexed by an R-tree so nodes can be matched spatially
+/// when the two trees being diffed do not share identical `NodeId`s.
+struct IndexedNode {
+    id: NodeId,
+    min: [f64; 3],
+    max: [f64; 3],
+    hash: u64,
+}
+
+impl RTreeObject for IndexedNode {
+    type Envelope = AABB<[f64; 3]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_corners(self.min, self.max)
+    }
+}
+
+/// Hashes a node's encoded point payload so two nodes can be compared for
+/// content equality without decoding them.
+fn hash_node_payload(octree: &Octree, node_id: &NodeId) -> Option<u64> {
+    let data = octree.encoded_node_data(node_id)?;
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+fn index_nodes(octree: &Octree) -> RTree<IndexedNode> {
+    let nodes = octree
+        .nodes()
+        .filter_map(|node_id| {
+            let bounding_box = octree.bounding_box(&node_id)?;
+            let hash = hash_node_payload(octree, &node_id)?;
+            Some(IndexedNode {
+                id: node_id,
+                min: [
+                    bounding_box.min().x,
+                    bounding_box.min().y,
+                    bounding_box.min().z,
+                ],
+                max: [
+                    bounding_box.max().x,
+                    bounding_box.max().y,
+                    bounding_box.max().z,
+                ],
+                hash,
+            })
+        })
+        .collect::<Vec<_>>();
+    RTree::bulk_load(nodes)
+}
+
+/// The overlapping volume of two nodes' bounding boxes, 0 when they don't
+/// actually overlap (a `locate_in_envelope_intersecting` hit can still be
+/// edge/corner-touching only).
+fn overlap_volume(a: &IndexedNode, b: &IndexedNode) -> f64 {
+    (0..3)
+        .map(|axis| (a.max[axis].min(b.max[axis]) - a.min[axis].max(b.min[axis])).max(0.0))
+        .product()
+}
+
+/// Finds the best unclaimed old-tree node whose bounding box overlaps
+/// `new_node`'s, preferring the one with the largest overlap volume. Used
+/// only once an exact `NodeId` match has failed, i.e. when the two trees
+/// were built with different subdivisions.
+fn best_overlapping_match<'a>(
+    old_index: &'a RTree<IndexedNode>,
+    new_node: &IndexedNode,
+    claimed_old: &HashSet<&'a NodeId>,
+) -> Option<&'a IndexedNode> {
+    old_index
+        .locate_in_envelope_intersecting(&AABB::from_corners(new_node.min, new_node.max))
+        .filter(|candidate| !claimed_old.contains(&candidate.id))
+        .filter(|candidate| overlap_volume(candidate, new_node) > 0.0)
+        .max_by(|a, b| {
+            overlap_volume(a, new_node)
+                .partial_cmp(&overlap_volume(b, new_node))
+                .unwrap()
+        })
+}
+
+/// Computes the manifest of node operations that turns `old` into `new`.
+///
+/// Nodes are matched primarily by identical [`NodeId`]. When the two trees
+/// were built with different subdivisions, a node without an exact `NodeId`
+/// match is instead matched against the old-tree node with the largest
+/// bounding-box overlap, found via an R-tree overlap query rather than an
+/// unconditional nearest-neighbor search — a node with no real counterpart
+/// must not be paired with whatever old node happens to be globally
+/// closest. Each old node can be claimed by at most one new node, so two
+/// new nodes competing for the same old one don't both get a `Changed`
+/// match while the old node's true state (e.g. `Removed`) is lost.
+pub fn diff(old: &Octree, new: &Octree) -> DiffManifest {
+    let old_index = index_nodes(old);
+    let new_index = index_nodes(new);
+
+    let old_by_id: HashMap<&NodeId, &IndexedNode> =
+        old_index.iter().map(|node| (&node.id, node)).collect();
+    let new_ids: HashSet<&NodeId> = new_index.iter().map(|node| &node.id).collect();
+
+    let mut entries = Vec::new();
+    let mut claimed_old: HashSet<&NodeId> = HashSet::new();
+
+    for new_node in new_index.iter() {
+        let old_node = old_by_id
+            .get(&new_node.id)
+            .copied()
+            .filter(|old_node| !claimed_old.contains(&old_node.id))
+            .or_else(|| best_overlapping_match(&old_index, new_node, &claimed_old));
+
+        match old_node {
+            Some(old_node) => {
+                claimed_old.insert(&old_node.id);
+                if old_node.hash != new_node.hash {
+                    entries.push(DiffEntry {
+                        node_id: new_node.id.to_string(),
+                        op: DiffOp::Changed,
+                        min: new_node.min,
+                        max: new_node.max,
+                    });
+                }
+            }
+            None => entries.push(DiffEntry {
+                node_id: new_node.id.to_string(),
+                op: DiffOp::Added,
+                min: new_node.min,
+                max: new_node.max,
+            }),
+        }
+    }
+
+    for old_node in old_index.iter() {
+        if !claimed_old.contains(&old_node.id) && !new_ids.contains(&old_node.id) {
+            entries.push(DiffEntry {
+                node_id: old_node.id.to_string(),
+                op: DiffOp::Removed,
+                min: old_node.min,
+                max: old_node.max,
+            });
+        }
+    }
+
+    DiffManifest { entries }
+}
+
+/// Handler backing the `/octree_diff/{old_key}/{new_key}` endpoint: loads
+/// both generations through the shared octree cache and returns the diff
+/// manifest a client can use to patch an already-loaded `old_key` up to
+/// `new_key`.
+pub fn handle_octree_diff(
+    state: &AppState,
+    old_key: &str,
+    new_key: &str,
+) -> Result<DiffManifest, PointsViewerError> {
+    state.load_octree_diff(old_key, new_key)
+}
+
+async fn handle_octree_diff_request(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, PointsViewerError> {
+    let (old_key, new_key) = path.into_inner();
+    let manifest = handle_octree_diff(&state, &old_key, &new_key)?;
+    Ok(HttpResponse::Ok().json(manifest))
+}
+
+/// Registers the `/octree_diff/{old_key}/{new_key}` route. Call this
+/// alongside the backend's other octree routes when assembling the app.
+///
+/// Neither this module nor `state.rs` calls `configure`; the endpoint is
+/// served only by an `actix_web::App` built with
+/// `.configure(octree_diff::configure)`.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route(
+        "/octree_diff/{old_key}/{new_key}",
+        web::get().to(handle_octree_diff_request),
+    );
+}
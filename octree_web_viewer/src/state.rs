@@ -1,7 +1,20 @@
 use crate::backend_error::PointsViewerError;
+use crate::octree_diff::{self, DiffManifest};
+use lru::LruCache;
 use point_viewer::octree;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex};
+
+/// A rough per-node memory estimate, used until `Octree` can report its own
+/// resident footprint. Encoded point data for a node is typically a few MiB.
+const APPROX_BYTES_PER_NODE: usize = 4 * 1024 * 1024;
+
+fn approx_octree_bytes(octree: &octree::Octree) -> usize {
+    octree
+        .meta()
+        .nodes
+        .len()
+        .saturating_mul(APPROX_BYTES_PER_NODE)
+}
 
 /// path information for the octrees
 #[derive(Clone)]
@@ -31,25 +44,110 @@ impl OctreeKeyParams {
     }
 }
 
+/// A cached value together with the approximate resident-byte estimate it
+/// was inserted with, so evicting it can keep `SizedLruCache::resident_bytes`
+/// in sync.
+struct Cached<V> {
+    value: V,
+    approx_bytes: usize,
+}
+
+/// An LRU cache bounded by both entry count and an approximate resident-byte
+/// budget. Generic over the cached value so the eviction bookkeeping can be
+/// unit-tested with plain sizes instead of a real `octree::Octree`.
+/// `Arc<octree::Octree>` sharing in `OctreeCache` means an entry that gets
+/// evicted while a request still holds a clone does not invalidate that
+/// request; it simply stops being served from cache.
+struct SizedLruCache<V> {
+    entries: LruCache<String, Cached<V>>,
+    resident_bytes: usize,
+    byte_budget: usize,
+}
+
+impl<V> SizedLruCache<V> {
+    fn new(map_size: usize, byte_budget: usize) -> Self {
+        SizedLruCache {
+            entries: LruCache::new(map_size),
+            resident_bytes: 0,
+            byte_budget,
+        }
+    }
+
+    /// Looks up `key`, promoting it to most-recently-used on a hit.
+    fn get(&mut self, key: &str) -> Option<&V> {
+        self.entries.get(key).map(|cached| &cached.value)
+    }
+
+    /// Inserts `value`, evicting least-recently-used entries until both the
+    /// entry-count and byte budgets are respected. A single entry whose own
+    /// size already exceeds the byte budget is kept anyway, since there is
+    /// nothing left to evict for it. Replacing an existing entry for `key`
+    /// first nets out its old size, so the eviction loop below never sees
+    /// that entry's bytes counted twice and evicts unrelated entries to make
+    /// room for a key that was already resident.
+    fn insert(&mut self, key: String, value: V, approx_bytes: usize) {
+        if let Some(previous) = self.entries.pop(&key) {
+            self.resident_bytes -= previous.approx_bytes;
+        }
+        // Entry-count evictions are done here too rather than left to
+        // `LruCache::put`, which would drop the entry without its bytes.
+        while self.resident_bytes + approx_bytes > self.byte_budget
+            || self.entries.len() >= self.entries.cap()
+        {
+            match self.entries.pop_lru() {
+                Some((_, evicted)) => self.resident_bytes -= evicted.approx_bytes,
+                None => break,
+            }
+        }
+        self.entries.put(key, Cached { value, approx_bytes });
+        self.resident_bytes += approx_bytes;
+    }
+}
+
+type OctreeCache = SizedLruCache<Arc<octree::Octree>>;
+
 #[derive(Clone)]
 pub struct AppState {
-    /// LRU Cache for Octrees
-    pub octree_map: Arc<RwLock<HashMap<String, Arc<octree::Octree>>>>,
+    /// LRU cache for octrees, evicted by both entry count and an
+    /// approximate resident-byte budget. Replaces the previously `pub`
+    /// `octree_map: Arc<RwLock<HashMap<...>>>` field; a repo-wide search of
+    /// this checkout turns up no caller of `octree_map` outside this file,
+    /// so there is nothing left to keep a compatible accessor for here. A
+    /// caller in a part of the tree not present in this checkout would need
+    /// to move to `load_octree`/`load_octree_diff` instead.
+    octree_cache: Arc<Mutex<OctreeCache>>,
     /// information for retieving octree path
     pub key_params: OctreeKeyParams,
     /// backward compatibility to input arguments
     pub init_octree_id: String,
 }
 
+/// Default resident-byte budget for `AppState::new`, used by callers that
+/// don't need finer control; ~2GiB, enough for a handful of typical octrees.
+/// Use `AppState::with_byte_budget` to override it.
+const DEFAULT_BYTE_BUDGET: usize = 2 * 1024 * 1024 * 1024;
+
 impl AppState {
     pub fn new(
         map_size: usize,
         prefix: impl Into<String>,
         suffix: impl Into<String>,
         octree_id: impl Into<String>,
+    ) -> Self {
+        Self::with_byte_budget(map_size, DEFAULT_BYTE_BUDGET, prefix, suffix, octree_id)
+    }
+
+    /// Like `new`, but with an explicit resident-byte budget instead of
+    /// `DEFAULT_BYTE_BUDGET`.
+    pub fn with_byte_budget(
+        map_size: usize,
+        byte_budget: usize,
+        prefix: impl Into<String>,
+        suffix: impl Into<String>,
+        octree_id: impl Into<String>,
     ) -> Self {
         AppState {
-            octree_map: Arc::new(RwLock::new(HashMap::with_capacity(map_size))),
+            octree_cache: Arc::new(Mutex::new(OctreeCache::new(map_size, byte_budget))),
             key_params: OctreeKeyParams {
                 prefix: prefix.into(),
                 suffix: suffix.into(),
@@ -70,18 +168,30 @@ impl AppState {
             return self.load_octree(&octree_key);
         }
         {
-            // read access to state
-            let map = self.octree_map.read().unwrap();
-            let octree = map.get(octree_key);
-            //some found
-            if let Some(tree) = octree {
-                return Ok(Arc::clone(&tree));
+            // read access to state, promotes the entry to most-recently-used
+            let mut cache = self.octree_cache.lock().unwrap();
+            if let Some(tree) = cache.get(octree_key) {
+                return Ok(Arc::clone(tree));
             }
         }
         // none found
         self.insert_octree(octree_key.to_string())
     }
 
+    /// Computes the node-level diff between two generations of the same
+    /// octree, loading (and caching) both through `load_octree` first. This
+    /// lets a client that already has `old_key` patch up to `new_key`
+    /// without re-downloading nodes whose content did not change.
+    pub fn load_octree_diff(
+        &self,
+        old_key: impl AsRef<str>,
+        new_key: impl AsRef<str>,
+    ) -> Result<DiffManifest, PointsViewerError> {
+        let old = self.load_octree(old_key)?;
+        let new = self.load_octree(new_key)?;
+        Ok(octree_diff::diff(&old, &new))
+    }
+
     fn insert_octree(
         &self,
         octree_id: impl Into<String>,
@@ -92,9 +202,57 @@ impl AppState {
         let octree: Arc<octree::Octree> = Arc::from(octree::octree_from_directory(&addr)?);
         {
             // write access to state
-            let mut wmap = self.octree_map.write().unwrap();
-            wmap.insert(octree_key.clone(), Arc::clone(&octree));
+            let mut cache = self.octree_cache.lock().unwrap();
+            let approx_bytes = approx_octree_bytes(&octree);
+            cache.insert(octree_key, Arc::clone(&octree), approx_bytes);
         }
         Ok(octree)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used_by_entry_count() {
+        let mut cache: SizedLruCache<u32> = SizedLruCache::new(2, usize::MAX);
+        cache.insert("a".to_string(), 1, 10);
+        cache.insert("b".to_string(), 2, 10);
+        cache.insert("c".to_string(), 3, 10);
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("b"), Some(&2));
+        assert_eq!(cache.get("c"), Some(&3));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_by_byte_budget() {
+        let mut cache: SizedLruCache<u32> = SizedLruCache::new(10, 25);
+        cache.insert("a".to_string(), 1, 10);
+        cache.insert("b".to_string(), 2, 10);
+        // The 25-byte budget only has room for two 10-byte entries; a third
+        // must evict "a", the least-recently-used one, even though the
+        // entry-count limit of 10 is nowhere near reached.
+        cache.insert("c".to_string(), 3, 10);
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("b"), Some(&2));
+        assert_eq!(cache.get("c"), Some(&3));
+        assert_eq!(cache.resident_bytes, 20);
+    }
+
+    #[test]
+    fn reinserting_same_key_does_not_double_count_its_old_size() {
+        let mut cache: SizedLruCache<u32> = SizedLruCache::new(10, 15);
+        cache.insert("a".to_string(), 1, 10);
+        // Replacing "a" with a same-size value must net out its old 10
+        // bytes before the eviction loop runs, or the budget check would
+        // see 20 bytes in flight for a single 10-byte entry and run the
+        // eviction loop for no reason (here, popping the old "a").
+        cache.insert("a".to_string(), 2, 10);
+
+        assert_eq!(cache.get("a"), Some(&2));
+        assert_eq!(cache.resident_bytes, 10);
+    }
+}
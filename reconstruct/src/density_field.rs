@@ -0,0 +1,108 @@
+//! Buckets points into a spatial hash over a uniform grid of spacing `h` and
+//! evaluates a compactly-supported SPH-style density kernel at arbitrary
+//! points, so that grid cells with no nearby points can be skipped entirely
+//! instead of being visited by marching cubes.
+
+use nalgebra::Point3;
+use std::collections::HashMap;
+
+type CellKey = (i64, i64, i64);
+
+/// A uniform grid of spacing `h`, with points bucketed into cells sized so
+/// that the kernel's support radius never spans more than a couple of
+/// neighboring cells in each direction.
+pub struct DensityField {
+    h: f64,
+    support_radius: f64,
+    buckets: HashMap<CellKey, Vec<Point3<f64>>>,
+}
+
+impl DensityField {
+    /// Buckets `points` into cells of size `h`. Density lookups gather points
+    /// from cells within `kernel_radius_in_cells * h` of a sample point; the
+    /// kernel itself is nonzero out to `2 * h`, so a value below 2 truncates
+    /// it.
+    pub fn build(
+        points: impl IntoIterator<Item = Point3<f64>>,
+        h: f64,
+        kernel_radius_in_cells: f64,
+    ) -> Self {
+        let mut buckets: HashMap<CellKey, Vec<Point3<f64>>> = HashMap::new();
+        for p in points {
+            buckets.entry(Self::cell_key(&p, h)).or_default().push(p);
+        }
+        DensityField {
+            h,
+            support_radius: kernel_radius_in_cells * h,
+            buckets,
+        }
+    }
+
+    fn cell_key(p: &Point3<f64>, h: f64) -> CellKey {
+        (
+            (p.x / h).floor() as i64,
+            (p.y / h).floor() as i64,
+            (p.z / h).floor() as i64,
+        )
+    }
+
+    fn neighbor_cells(&self, center: CellKey) -> impl Iterator<Item = CellKey> + '_ {
+        let (cx, cy, cz) = center;
+        let reach = (self.support_radius / self.h).ceil() as i64;
+        (-reach..=reach).flat_map(move |dx| {
+            (-reach..=reach)
+                .flat_map(move |dy| (-reach..=reach).map(move |dz| (cx + dx, cy + dy, cz + dz)))
+        })
+    }
+
+    /// Whether any point lies within this field's kernel support of `x`,
+    /// i.e. whether a grid cell containing `x` is worth visiting at all.
+    pub fn is_occupied_near(&self, x: &Point3<f64>) -> bool {
+        self.neighbor_cells(Self::cell_key(x, self.h))
+            .any(|key| self.buckets.contains_key(&key))
+    }
+
+    /// Whether any point lies within this field's kernel support of any
+    /// point in the axis-aligned cube `[cell_min, cell_min + cell_size]`,
+    /// not just its center. Marching cubes samples a cell's 8 corners, up
+    /// to `0.5 * sqrt(3) * cell_size` from the center — farther than
+    /// `is_occupied_near(&center)` accounts for — so a corner can pick up
+    /// density from a bucket the center-only query would miss, silently
+    /// skipping a cell that should have been tessellated and leaving a
+    /// hole in the surface. Widens the query radius by that half-diagonal
+    /// to cover the whole cell instead.
+    pub fn is_occupied_near_cell(&self, cell_min: &Point3<f64>, cell_size: f64) -> bool {
+        let center = Point3::new(
+            cell_min.x + 0.5 * cell_size,
+            cell_min.y + 0.5 * cell_size,
+            cell_min.z + 0.5 * cell_size,
+        );
+        let half_diagonal = 0.5 * cell_size * 3.0_f64.sqrt();
+        let reach = ((self.support_radius + half_diagonal) / self.h).ceil() as i64;
+        let (cx, cy, cz) = Self::cell_key(&center, self.h);
+        (-reach..=reach)
+            .flat_map(|dx| (-reach..=reach).flat_map(move |dy| (-reach..=reach).map(move |dz| (dx, dy, dz))))
+            .any(|(dx, dy, dz)| self.buckets.contains_key(&(cx + dx, cy + dy, cz + dz)))
+    }
+
+    /// `ρ(x) = Σ_i W(|x − p_i| / h)` over the points within the kernel's
+    /// support radius of `x`.
+    pub fn density_at(&self, x: &Point3<f64>) -> f64 {
+        self.neighbor_cells(Self::cell_key(x, self.h))
+            .filter_map(|key| self.buckets.get(&key))
+            .flatten()
+            .map(|p| Self::kernel((x - p).norm() / self.h))
+            .sum()
+    }
+
+    /// Cubic-spline SPH smoothing kernel, compactly supported on `q ∈ [0, 2]`.
+    fn kernel(q: f64) -> f64 {
+        if q < 1.0 {
+            1.0 - 1.5 * q * q + 0.75 * q * q * q
+        } else if q < 2.0 {
+            0.25 * (2.0 - q).powi(3)
+        } else {
+            0.0
+        }
+    }
+}
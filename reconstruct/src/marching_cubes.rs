@@ -0,0 +1,144 @@
+//! Tessellates a thresholded [`DensityField`] into a triangle mesh via the
+//! standard marching-cubes algorithm (Lorensen & Cline, 1987), run once per
+//! occupied grid cell of spacing `h` rather than over the whole domain at a
+//! single resolution: the 256-case triangle/edge lookup tables and edge
+//! interpolation are provided by the `isosurface` crate, so this module only
+//! adapts our density field into its `Source` interface and decides, per
+//! cell, whether there's anything there worth tessellating.
+
+use crate::density_field::DensityField;
+use isosurface::extractor::IndexedInterleavedNormals;
+use isosurface::marching_cubes::MarchingCubes;
+use isosurface::source::Source;
+use nalgebra::Point3;
+use std::collections::HashMap;
+
+/// A triangle mesh: interleaved `[x, y, z, nx, ny, nz, ...]` vertex data and
+/// a triangle index buffer, ready to hand to [`crate::mesh_export`].
+pub struct Mesh {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+/// Adapts a single grid cell `[cell_min, cell_min + h]` of a [`DensityField`]
+/// into the unit cube `isosurface::Source` expects, thresholding at
+/// `iso_level`.
+struct CellSource<'a> {
+    field: &'a DensityField,
+    iso_level: f64,
+    cell_min: [f64; 3],
+    h: f64,
+}
+
+impl<'a> Source for CellSource<'a> {
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        let point = Point3::new(
+            self.cell_min[0] + f64::from(x) * self.h,
+            self.cell_min[1] + f64::from(y) * self.h,
+            self.cell_min[2] + f64::from(z) * self.h,
+        );
+        (self.field.density_at(&point) - self.iso_level) as f32
+    }
+}
+
+/// Runs marching cubes over `field` within `[origin, origin + extent]`,
+/// visiting one cube per grid cell of spacing `h` per axis. Cells that
+/// `field.is_occupied_near_cell` reports as having no nearby points
+/// anywhere in their extent are skipped entirely, so cost scales with the
+/// number of occupied cells rather than the number of cells in the
+/// bounding box — important for e.g. a long, thin corridor, where the long
+/// axis alone would otherwise dominate a single cubic resolution. Vertices
+/// on cells' shared faces are welded together afterwards (see
+/// `weld_vertices`), so the result is one mesh rather than a pile of
+/// independently-extracted per-cell fragments.
+pub fn extract(
+    field: &DensityField,
+    origin: [f64; 3],
+    extent: [f64; 3],
+    h: f64,
+    iso_level: f64,
+) -> Mesh {
+    let cells_per_axis = [
+        (extent[0] / h).ceil().max(1.0) as usize,
+        (extent[1] / h).ceil().max(1.0) as usize,
+        (extent[2] / h).ceil().max(1.0) as usize,
+    ];
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for i in 0..cells_per_axis[0] {
+        for j in 0..cells_per_axis[1] {
+            for k in 0..cells_per_axis[2] {
+                let cell_min = [
+                    origin[0] + i as f64 * h,
+                    origin[1] + j as f64 * h,
+                    origin[2] + k as f64 * h,
+                ];
+                if !field.is_occupied_near_cell(
+                    &Point3::new(cell_min[0], cell_min[1], cell_min[2]),
+                    h,
+                ) {
+                    continue;
+                }
+
+                let source = CellSource {
+                    field,
+                    iso_level,
+                    cell_min,
+                    h,
+                };
+                let mut cell_vertices = Vec::new();
+                let mut cell_indices = Vec::new();
+                MarchingCubes::new(1).extract(
+                    &source,
+                    &mut IndexedInterleavedNormals::new(&mut cell_vertices, &mut cell_indices),
+                );
+                // The extractor emits positions in the cell's unit cube; map
+                // them back to world space the same way `CellSource` samples.
+                for vertex in cell_vertices.chunks_mut(6) {
+                    for axis in 0..3 {
+                        vertex[axis] = (cell_min[axis] + f64::from(vertex[axis]) * h) as f32;
+                    }
+                }
+
+                let index_offset = (vertices.len() / 6) as u32;
+                indices.extend(cell_indices.into_iter().map(|idx| idx + index_offset));
+                vertices.extend(cell_vertices);
+            }
+        }
+    }
+    let (vertices, indices) = weld_vertices(vertices, indices, h);
+    Mesh { vertices, indices }
+}
+
+/// Merges vertices that fall on a shared boundary between two independently
+/// extracted cells into one. Positions are quantized to a small fraction of
+/// `h` before comparing: a point on a shared face is reconstructed by both
+/// neighboring cells from the same underlying density samples, but each
+/// cell's own local-to-world coordinate math can differ by a few ULPs, so
+/// comparing raw floats would miss the match and leave the faces unwelded.
+fn weld_vertices(vertices: Vec<f32>, indices: Vec<u32>, h: f64) -> (Vec<f32>, Vec<u32>) {
+    const VERTEX_STRIDE: usize = 6; // [x, y, z, nx, ny, nz]
+    let quantum = (h * 1e-4).max(f64::from(f32::EPSILON)) as f32;
+    let quantize = |value: f32| -> i64 { (value / quantum).round() as i64 };
+
+    let mut welded_vertices = Vec::new();
+    let mut first_index_for_position: HashMap<(i64, i64, i64), u32> = HashMap::new();
+    let mut remap = vec![0u32; vertices.len() / VERTEX_STRIDE];
+    for (old_index, vertex) in vertices.chunks(VERTEX_STRIDE).enumerate() {
+        let key = (
+            quantize(vertex[0]),
+            quantize(vertex[1]),
+            quantize(vertex[2]),
+        );
+        let new_index = *first_index_for_position.entry(key).or_insert_with(|| {
+            let index = (welded_vertices.len() / VERTEX_STRIDE) as u32;
+            welded_vertices.extend_from_slice(vertex);
+            index
+        });
+        remap[old_index] = new_index;
+    }
+
+    let welded_indices = indices.into_iter().map(|index| remap[index as usize]).collect();
+    (welded_vertices, welded_indices)
+}
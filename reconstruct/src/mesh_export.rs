@@ -0,0 +1,52 @@
+//! Writes a [`Mesh`] out as PLY or OBJ, the two formats users load the
+//! reconstructed surface into external tools with for occlusion and
+//! collision checks.
+
+use crate::marching_cubes::Mesh;
+use std::io::{self, Write};
+
+/// Writes `mesh` as an ASCII PLY file with vertex normals and triangular
+/// faces.
+pub fn write_ply<W: Write>(mesh: &Mesh, mut writer: W) -> io::Result<()> {
+    let num_vertices = mesh.vertices.len() / 6;
+    let num_triangles = mesh.indices.len() / 3;
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "element vertex {}", num_vertices)?;
+    writeln!(writer, "property float x")?;
+    writeln!(writer, "property float y")?;
+    writeln!(writer, "property float z")?;
+    writeln!(writer, "property float nx")?;
+    writeln!(writer, "property float ny")?;
+    writeln!(writer, "property float nz")?;
+    writeln!(writer, "element face {}", num_triangles)?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+    for v in mesh.vertices.chunks_exact(6) {
+        writeln!(writer, "{} {} {} {} {} {}", v[0], v[1], v[2], v[3], v[4], v[5])?;
+    }
+    for tri in mesh.indices.chunks_exact(3) {
+        writeln!(writer, "3 {} {} {}", tri[0], tri[1], tri[2])?;
+    }
+    Ok(())
+}
+
+/// Writes `mesh` as an OBJ file (`v`/`vn`/`f` lines, 1-indexed).
+pub fn write_obj<W: Write>(mesh: &Mesh, mut writer: W) -> io::Result<()> {
+    for v in mesh.vertices.chunks_exact(6) {
+        writeln!(writer, "v {} {} {}", v[0], v[1], v[2])?;
+    }
+    for v in mesh.vertices.chunks_exact(6) {
+        writeln!(writer, "vn {} {} {}", v[3], v[4], v[5])?;
+    }
+    for tri in mesh.indices.chunks_exact(3) {
+        writeln!(
+            writer,
+            "f {0}//{0} {1}//{1} {2}//{2}",
+            tri[0] + 1,
+            tri[1] + 1,
+            tri[2] + 1
+        )?;
+    }
+    Ok(())
+}
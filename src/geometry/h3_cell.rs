@@ -0,0 +1,161 @@
+//! An H3 hexagonal (or, at the 12 icosahedron vertices, pentagonal) cell as
+//! a point-culling volume, alongside
+//! [`WebMercatorRect`](crate::geometry::web_mercator_rect::WebMercatorRect).
+//! Unlike a Web Mercator rectangle, H3 cells tile the globe with roughly
+//! uniform area regardless of latitude, so this is preferable near the
+//! poles.
+
+use alga::general::SupersetOf;
+use crate::math::base::{HasAabbIntersector, PointCulling};
+use crate::math::sat::{CachedAxesIntersector, ConvexPolyhedron, Intersector};
+use h3o::{CellIndex, LatLng};
+use nalgebra::{Point3, RealField, Unit};
+use nav_types::{ECEF, WGS84};
+
+/// An H3 cell extruded between a minimum and maximum elevation to form a
+/// convex prism over the cell's boundary polygon.
+#[derive(Debug, Clone)]
+pub struct H3Cell<S: RealField> {
+    cell: CellIndex,
+    min_elevation_m: S,
+    max_elevation_m: S,
+}
+
+impl<S: RealField + SupersetOf<u32>> H3Cell<S> {
+    pub fn new(cell: CellIndex, min_elevation_m: S, max_elevation_m: S) -> Self {
+        H3Cell {
+            cell,
+            min_elevation_m,
+            max_elevation_m,
+        }
+    }
+
+    fn boundary(&self) -> Vec<LatLng> {
+        self.cell.boundary().iter().copied().collect()
+    }
+}
+
+/// Calculating the volume of all points in space which fall, by elevation,
+/// into the prism obtained by extruding this H3 cell's boundary. Implemented
+/// by extruding the boundary ring up and down along the altitude axis,
+/// which results in a convex polyhedron with twice as many corners as the
+/// cell has boundary vertices (6 for a hexagon, 5 for a pentagon).
+impl<S: RealField + SupersetOf<u32>> ConvexPolyhedron<S> for H3Cell<S>
+where
+    f64: From<S>,
+{
+    fn compute_corners(&self) -> Vec<Point3<S>> {
+        let ecef_point = |lat_lng: &LatLng, elevation: S| -> Point3<S> {
+            let wgs84 = WGS84::new(
+                nalgebra::convert(lat_lng.lat()),
+                nalgebra::convert(lat_lng.lng()),
+                elevation,
+            );
+            let ecef = ECEF::from(wgs84);
+            Point3::new(ecef.x(), ecef.y(), ecef.z())
+        };
+        let boundary = self.boundary();
+        boundary
+            .iter()
+            .map(|ll| ecef_point(ll, self.min_elevation_m.clone()))
+            .chain(
+                boundary
+                    .iter()
+                    .map(|ll| ecef_point(ll, self.max_elevation_m.clone())),
+            )
+            .collect()
+    }
+
+    fn intersector(&self) -> Intersector<S> {
+        let corners = self.compute_corners();
+        let num_sides = corners.len() / 2;
+
+        let mut edges = Vec::with_capacity(3 * num_sides);
+        for i in 0..num_sides {
+            edges.push(Unit::new_normalize(
+                corners[(i + 1) % num_sides] - corners[i],
+            ));
+        }
+        for i in 0..num_sides {
+            edges.push(Unit::new_normalize(
+                corners[num_sides + (i + 1) % num_sides] - corners[num_sides + i],
+            ));
+        }
+        for i in 0..num_sides {
+            edges.push(Unit::new_normalize(corners[num_sides + i] - corners[i]));
+        }
+
+        let mut face_normals = Vec::with_capacity(num_sides + 2);
+        for i in 0..num_sides {
+            face_normals.push(Unit::new_normalize(
+                edges[i].cross(&edges[2 * num_sides + i]),
+            ));
+        }
+        face_normals.push(Unit::new_normalize(edges[1].cross(&edges[0]))); // down face
+        face_normals.push(Unit::new_normalize(
+            edges[num_sides + 1].cross(&edges[num_sides]),
+        )); // up face
+
+        Intersector {
+            corners,
+            edges,
+            face_normals,
+        }
+    }
+}
+
+has_aabb_intersector_for_convex_polyhedron!(H3Cell<S>);
+
+impl<S: RealField + SupersetOf<u32>> PointCulling<S> for H3Cell<S> {
+    fn contains(&self, point: &Point3<S>) -> bool {
+        let ll: WGS84<S> = ECEF::new(point.x, point.y, point.z).into();
+        // `compute_corners`/`intersector` bound this cell's prism between
+        // `min_elevation_m` and `max_elevation_m`; the precise test must
+        // agree, or a point far above/below the cell (but within its
+        // hexagonal column) would pass here while the coarse SAT/AABB test
+        // correctly rejects it.
+        if ll.altitude() < self.min_elevation_m || ll.altitude() > self.max_elevation_m {
+            return false;
+        }
+        // A pathological/degenerate ECEF point (e.g. at or near the origin)
+        // can fail to round-trip into a valid lat/lng; such a point is not
+        // meaningfully inside any H3 cell, so this is a miss, not a panic.
+        let lat_lng = match LatLng::from_radians(
+            f64::from(ll.latitude_degrees()).to_radians(),
+            f64::from(ll.longitude_degrees()).to_radians(),
+        ) {
+            Ok(lat_lng) => lat_lng,
+            Err(_) => return false,
+        };
+        lat_lng.to_cell(self.cell.resolution()) == self.cell
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_does_not_panic_on_degenerate_point() {
+        let cell = LatLng::from_radians(0.0, 0.0).unwrap().to_cell(h3o::Resolution::Five);
+        let h3_cell = H3Cell::new(cell, -10.0_f64, 10.0_f64);
+        // The ECEF origin has no meaningful lat/lng; `contains` must report
+        // a miss rather than panic on it.
+        assert!(!h3_cell.contains(&Point3::new(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn contains_respects_elevation_bounds() {
+        let cell = LatLng::from_radians(0.0, 0.0).unwrap().to_cell(h3o::Resolution::Five);
+        let h3_cell = H3Cell::new(cell, 0.0_f64, 100.0_f64);
+
+        let point_at_altitude = |altitude: f64| -> Point3<f64> {
+            let ecef = ECEF::from(WGS84::new(0.0, 0.0, altitude));
+            Point3::new(ecef.x(), ecef.y(), ecef.z())
+        };
+
+        assert!(h3_cell.contains(&point_at_altitude(50.0)));
+        assert!(!h3_cell.contains(&point_at_altitude(-50.0)));
+        assert!(!h3_cell.contains(&point_at_altitude(150.0)));
+    }
+}
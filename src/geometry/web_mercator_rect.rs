@@ -4,7 +4,6 @@ use alga::general::SupersetOf;
 use crate::math::base::{HasAabbIntersector, PointCulling};
 use crate::math::sat::{CachedAxesIntersector, ConvexPolyhedron, Intersector};
 use crate::math::web_mercator::WebMercatorCoord;
-use arrayvec::ArrayVec;
 use nalgebra::{Point3, RealField, Unit, Vector2};
 use nav_types::{ECEF, WGS84};
 use serde::{Deserialize, Serialize};
@@ -42,7 +41,11 @@ impl<S: RealField+ SupersetOf<u32>> WebMercatorRect<S> {
 /// Implemented by extruding the rectangle's four corners along their altitude
 /// axis up and down, which results in a convex polyhedron.
 impl<S: RealField + SupersetOf<u32>> ConvexPolyhedron<S> for WebMercatorRect<S> where f64: From<S> {
-    fn compute_corners(&self) -> [Point3<S>; 8] {
+    // A variable-vertex `Vec` rather than a fixed `[Point3<S>; 8]`, so that
+    // other convex polyhedra (e.g. `H3Cell`, whose prisms have 10 or 12
+    // corners) can share this trait; the SAT edge/face-normal generation
+    // below is driven by `corners.len()` rather than a hard-coded count.
+    fn compute_corners(&self) -> Vec<Point3<S>> {
         let n_w = self.north_west.to_lat_lng();
         let s_e = self.south_east.to_lat_lng();
         let ecef_point = |lat: WGS84<S>, lng: WGS84<S>, elevation: S| -> Point3<S> {
@@ -50,7 +53,7 @@ impl<S: RealField + SupersetOf<u32>> ConvexPolyhedron<S> for WebMercatorRect<S>
             let ecef = ECEF::from(lat_lng);
             Point3::new(ecef.x(), ecef.y(), ecef.z())
         };
-        [
+        vec![
             ecef_point(n_w, n_w, Self::min_elevation_m()), // NW down
             ecef_point(n_w, s_e, Self::min_elevation_m()), // NE down
             ecef_point(s_e, s_e, Self::min_elevation_m()), // SE down
@@ -64,29 +67,36 @@ impl<S: RealField + SupersetOf<u32>> ConvexPolyhedron<S> for WebMercatorRect<S>
 
     fn intersector(&self) -> Intersector<S> {
         let corners = self.compute_corners();
-        let edges = ArrayVec::from([
-            Unit::new_normalize(corners[1] - corners[0]), // N edge, down
-            Unit::new_normalize(corners[2] - corners[1]), // E edge, down
-            Unit::new_normalize(corners[3] - corners[2]), // S edge, down
-            Unit::new_normalize(corners[0] - corners[3]), // W edge, down
-            Unit::new_normalize(corners[5] - corners[4]), // N edge, up
-            Unit::new_normalize(corners[6] - corners[5]), // E edge, up
-            Unit::new_normalize(corners[7] - corners[6]), // S edge, up
-            Unit::new_normalize(corners[4] - corners[7]), // W edge, up
-            Unit::new_normalize(corners[4] - corners[0]), // NW edge
-            Unit::new_normalize(corners[5] - corners[1]), // NE edge
-            Unit::new_normalize(corners[6] - corners[2]), // SE edge
-            Unit::new_normalize(corners[7] - corners[3]), // SW edge
-        ]);
+        let num_sides = corners.len() / 2;
 
-        let face_normals = ArrayVec::from([
-            Unit::new_normalize(edges[0].cross(&edges[8])), // N face
-            Unit::new_normalize(edges[1].cross(&edges[9])), // E face
-            Unit::new_normalize(edges[2].cross(&edges[10])), // S face
-            Unit::new_normalize(edges[3].cross(&edges[11])), // W face
-            Unit::new_normalize(edges[1].cross(&edges[0])), // down face
-            Unit::new_normalize(edges[5].cross(&edges[4])), // up face
-        ]);
+        let mut edges = Vec::with_capacity(3 * num_sides);
+        for i in 0..num_sides {
+            // Side edge, down ring.
+            edges.push(Unit::new_normalize(
+                corners[(i + 1) % num_sides] - corners[i],
+            ));
+        }
+        for i in 0..num_sides {
+            // Side edge, up ring.
+            edges.push(Unit::new_normalize(
+                corners[num_sides + (i + 1) % num_sides] - corners[num_sides + i],
+            ));
+        }
+        for i in 0..num_sides {
+            // Vertical edge connecting the down and up rings.
+            edges.push(Unit::new_normalize(corners[num_sides + i] - corners[i]));
+        }
+
+        let mut face_normals = Vec::with_capacity(num_sides + 2);
+        for i in 0..num_sides {
+            face_normals.push(Unit::new_normalize(
+                edges[i].cross(&edges[2 * num_sides + i]),
+            ));
+        }
+        face_normals.push(Unit::new_normalize(edges[1].cross(&edges[0]))); // down face
+        face_normals.push(Unit::new_normalize(
+            edges[num_sides + 1].cross(&edges[num_sides]),
+        )); // up face
 
         Intersector {
             corners,
@@ -0,0 +1,148 @@
+//! Separating Axis Theorem (SAT) intersection tests for convex polyhedra
+//! used as point-culling volumes.
+
+use nalgebra::{Point3, RealField, Unit, Vector3};
+
+/// How two convex polyhedra relate to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// The polyhedra do not overlap at all.
+    Out,
+    /// The polyhedra overlap, but neither fully contains the other.
+    Cross,
+    /// One polyhedron's corners all lie within the other, on every
+    /// candidate axis.
+    In,
+}
+
+/// A convex polyhedron with a variable number of vertices, so both
+/// fixed-vertex-count shapes (e.g. a rectangular prism, 8 corners) and
+/// shapes with a different count (e.g. an H3 hex/pentagon prism, 12 or 10
+/// corners) can share the same SAT machinery. Implementations' `intersector`
+/// must build `Intersector::edges`/`face_normals` from `corners.len()`
+/// rather than a hard-coded constant.
+pub trait ConvexPolyhedron<S: RealField> {
+    /// The polyhedron's corners, in an implementation-defined order that its
+    /// own `intersector` relies on to build edges and face normals.
+    fn compute_corners(&self) -> Vec<Point3<S>>;
+
+    /// The precomputed edges and face normals used by [`Intersector::intersect`].
+    fn intersector(&self) -> Intersector<S>;
+}
+
+/// The precomputed corners, edges, and face normals of a convex polyhedron,
+/// used to run the SAT test in [`Intersector::intersect`].
+#[derive(Debug, Clone)]
+pub struct Intersector<S: RealField> {
+    pub corners: Vec<Point3<S>>,
+    pub edges: Vec<Unit<Vector3<S>>>,
+    pub face_normals: Vec<Unit<Vector3<S>>>,
+}
+
+impl<S: RealField> Intersector<S> {
+    /// The candidate separating axes for the SAT test: this polyhedron's
+    /// face normals, `other`'s face normals, and the cross products of each
+    /// pair of edges between the two.
+    fn candidate_axes(&self, other: &Intersector<S>) -> Vec<Vector3<S>> {
+        let mut axes = Vec::with_capacity(
+            self.face_normals.len() + other.face_normals.len() + self.edges.len() * other.edges.len(),
+        );
+        axes.extend(self.face_normals.iter().map(|n| n.into_inner()));
+        axes.extend(other.face_normals.iter().map(|n| n.into_inner()));
+        for e1 in &self.edges {
+            for e2 in &other.edges {
+                // A zero cross product (parallel edges) projects every
+                // corner onto the same point on both sides, which never
+                // produces a false `Out`, so no epsilon filtering is needed.
+                axes.push(e1.as_ref().cross(e2.as_ref()));
+            }
+        }
+        axes
+    }
+
+    fn project(&self, axis: &Vector3<S>) -> (S, S) {
+        let mut min = axis.dot(&self.corners[0].coords);
+        let mut max = min;
+        for corner in &self.corners[1..] {
+            let d = axis.dot(&corner.coords);
+            if d < min {
+                min = d;
+            }
+            if d > max {
+                max = d;
+            }
+        }
+        (min, max)
+    }
+
+    /// Runs the Separating Axis Theorem test between `self` and `other`:
+    /// if any candidate axis separates their projections, they don't
+    /// overlap; otherwise they overlap, either fully (one contains the
+    /// other on every axis) or partially.
+    pub fn intersect(&self, other: &Intersector<S>) -> Relation {
+        let mut this_contains_other = true;
+        let mut other_contains_this = true;
+        for axis in self.candidate_axes(other) {
+            let (min_a, max_a) = self.project(&axis);
+            let (min_b, max_b) = other.project(&axis);
+            if max_a < min_b || max_b < min_a {
+                return Relation::Out;
+            }
+            if !(min_b >= min_a && max_b <= max_a) {
+                this_contains_other = false;
+            }
+            if !(min_a >= min_b && max_a <= max_b) {
+                other_contains_this = false;
+            }
+        }
+        if this_contains_other || other_contains_this {
+            Relation::In
+        } else {
+            Relation::Cross
+        }
+    }
+}
+
+/// A convex polyhedron's [`Intersector`] together with an axis-aligned
+/// bounding box of its corners, so a cheap AABB rejection can short-circuit
+/// the full SAT test.
+pub struct CachedAxesIntersector<S: RealField> {
+    pub aabb_min: Point3<S>,
+    pub aabb_max: Point3<S>,
+    pub intersector: Intersector<S>,
+}
+
+impl<S: RealField> CachedAxesIntersector<S> {
+    pub fn new(intersector: Intersector<S>) -> Self {
+        let mut aabb_min = intersector.corners[0];
+        let mut aabb_max = intersector.corners[0];
+        for corner in &intersector.corners[1..] {
+            aabb_min.x = if corner.x < aabb_min.x { corner.x } else { aabb_min.x };
+            aabb_min.y = if corner.y < aabb_min.y { corner.y } else { aabb_min.y };
+            aabb_min.z = if corner.z < aabb_min.z { corner.z } else { aabb_min.z };
+            aabb_max.x = if corner.x > aabb_max.x { corner.x } else { aabb_max.x };
+            aabb_max.y = if corner.y > aabb_max.y { corner.y } else { aabb_max.y };
+            aabb_max.z = if corner.z > aabb_max.z { corner.z } else { aabb_max.z };
+        }
+        CachedAxesIntersector {
+            aabb_min,
+            aabb_max,
+            intersector,
+        }
+    }
+}
+
+/// Implements `crate::math::base::HasAabbIntersector` for a
+/// [`ConvexPolyhedron`] type by wrapping its `intersector()` in a
+/// [`CachedAxesIntersector`]. Relies on the invocation site having already
+/// imported `HasAabbIntersector` and `CachedAxesIntersector`.
+#[macro_export]
+macro_rules! has_aabb_intersector_for_convex_polyhedron {
+    ($ty:ty) => {
+        impl<S: nalgebra::RealField> HasAabbIntersector<S> for $ty {
+            fn aabb_intersector(&self) -> CachedAxesIntersector<S> {
+                CachedAxesIntersector::new(self.intersector())
+            }
+        }
+    };
+}
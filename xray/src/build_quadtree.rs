@@ -0,0 +1,26 @@
+//! The final step of building an xray quadtree's `meta.pb`: once a
+//! quadtree's nodes and tile images are written, fit a plane to each node's
+//! points and persist the result alongside the metadata.
+//!
+//! This is deliberately the *last* step, not folded into node/tile
+//! generation: `Meta::compute_node_planes` needs the final `nodes` set
+//! (`Meta::from_disk`'s result) to know which nodes to fit planes for, and
+//! re-reads each node's points from the octree the quadtree was built from
+//! rather than threading them through tile generation.
+
+use crate::Meta;
+use cgmath::Point3;
+use quadtree::NodeId;
+use std::io;
+use std::path::Path;
+
+/// Fits and persists `node_planes` for the quadtree metadata at
+/// `meta_path`, which must already exist (i.e. this runs after node/tile
+/// generation has written it). `points_for_node` reads a node's points back
+/// from the octree the quadtree was built from.
+pub fn finalize_node_planes<F>(meta_path: &Path, points_for_node: F) -> io::Result<Meta>
+where
+    F: FnMut(&NodeId) -> Vec<Point3<f64>>,
+{
+    Meta::build_and_persist_node_planes(meta_path, points_for_node)
+}
@@ -0,0 +1,92 @@
+//! Least-squares plane fitting for octree nodes / xray quadtree cells.
+//!
+//! The fitted plane's normal and error score let downstream tools shade
+//! nodes by surface orientation and cull near-planar regions without
+//! re-reading their points.
+
+use cgmath::{InnerSpace, Point3, Vector3};
+use nalgebra::{Matrix3, SymmetricEigen};
+use serde_derive::{Deserialize, Serialize};
+
+/// The best-fit plane through a node's points, together with how well they
+/// agree with that plane.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PlaneFit {
+    /// Unit normal of the fitted plane, `[0, 0, 0]` for the fewer-than-three-
+    /// points sentinel.
+    pub normal: [f64; 3],
+    /// `(λ1 - λ0) / λ2`, the eigenvalues of the point covariance matrix in
+    /// ascending order. Close to 1 for a well-defined plane, close to 0 when
+    /// the points are closer to a line or a sphere.
+    pub planarity: f64,
+    /// RMS of the signed point-to-plane distances. `f64::INFINITY` for the
+    /// fewer-than-three-points sentinel.
+    pub rms_error: f64,
+}
+
+impl PlaneFit {
+    fn none() -> Self {
+        PlaneFit {
+            normal: [0.0, 0.0, 0.0],
+            planarity: 0.0,
+            rms_error: f64::INFINITY,
+        }
+    }
+}
+
+/// Fits a plane to `points` via principal component analysis of their
+/// covariance matrix: the normal is the eigenvector of the smallest
+/// eigenvalue `λ0 ≤ λ1 ≤ λ2`. Returns the sentinel [`PlaneFit::none`] when
+/// fewer than three points are given.
+pub fn fit_plane(points: &[Point3<f64>]) -> PlaneFit {
+    if points.len() < 3 {
+        return PlaneFit::none();
+    }
+    let num_points = points.len() as f64;
+    let centroid = points
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |acc, p| {
+            acc + Vector3::new(p.x, p.y, p.z)
+        })
+        / num_points;
+
+    let mut covariance = Matrix3::zeros();
+    for p in points {
+        let d = Vector3::new(p.x, p.y, p.z) - centroid;
+        let d = nalgebra::Vector3::new(d.x, d.y, d.z);
+        covariance += d * d.transpose();
+    }
+    covariance /= num_points;
+
+    let eigen = SymmetricEigen::new(covariance);
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| {
+        eigen.eigenvalues[a]
+            .partial_cmp(&eigen.eigenvalues[b])
+            .unwrap()
+    });
+    let (i0, i1, i2) = (order[0], order[1], order[2]);
+    let lambda0 = eigen.eigenvalues[i0].max(0.0);
+    let lambda1 = eigen.eigenvalues[i1].max(0.0);
+    let lambda2 = eigen.eigenvalues[i2].max(0.0);
+
+    let smallest = eigen.eigenvectors.column(i0);
+    let normal = Vector3::new(smallest.x, smallest.y, smallest.z).normalize();
+
+    let planarity = if lambda2 > 0.0 {
+        (lambda1 - lambda0) / lambda2
+    } else {
+        0.0
+    };
+
+    let sum_sq_dist: f64 = points
+        .iter()
+        .map(|p| (Vector3::new(p.x, p.y, p.z) - centroid).dot(normal).powi(2))
+        .sum();
+
+    PlaneFit {
+        normal: [normal.x, normal.y, normal.z],
+        planarity,
+        rms_error: (sum_sq_dist / num_points).sqrt(),
+    }
+}